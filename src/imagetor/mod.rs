@@ -3,6 +3,7 @@ use std::{error::Error, vec};
 
 use image::{imageops, DynamicImage, GenericImageView, ImageBuffer, Rgba};
 
+pub mod tilecache;
 pub mod utils;
 
 const CHANNELS: usize = 4;
@@ -93,6 +94,36 @@ pub fn fit_center(image1: &DynamicImage, image2: &DynamicImage) -> DynamicImage
     image1.resize(nwidth, nheight, imageops::FilterType::Lanczos3)
 }
 
+/// Rasterize an SVG logo at exactly the pixel size [`fit_center`] would choose
+/// for `image`, so a vector watermark stays crisp at any photo size instead of
+/// being upscaled from a bitmap. Returns an RGBA `DynamicImage` whose alpha
+/// flows unchanged into [`to_tensor`] and [`addwatermark`].
+pub fn rasterize_svg_fit(
+    svg_data: &[u8],
+    image: &DynamicImage,
+) -> Result<DynamicImage, Box<dyn Error>> {
+    use resvg::tiny_skia::{Pixmap, Transform};
+    use resvg::usvg::{Options, Tree};
+
+    let tree = Tree::from_data(svg_data, &Options::default())?;
+    let size = tree.size();
+    let (svg_width, svg_height) = (size.width(), size.height());
+
+    let (width2, height2) = image.dimensions();
+    let factor = mean_center(svg_width as u32, svg_height as u32, width2, height2);
+    let nwidth = (svg_width * factor) as u32;
+    let nheight = (svg_height * factor) as u32;
+
+    let mut pixmap =
+        Pixmap::new(nwidth, nheight).ok_or_else(|| Box::new(RasterizeError) as Box<dyn Error>)?;
+    let transform = Transform::from_scale(nwidth as f32 / svg_width, nheight as f32 / svg_height);
+    resvg::render(&tree, transform, &mut pixmap.as_mut());
+
+    let buffer = ImageBuffer::<Rgba<u8>, _>::from_raw(nwidth, nheight, pixmap.take())
+        .ok_or_else(|| Box::new(RasterizeError) as Box<dyn Error>)?;
+    Ok(DynamicImage::ImageRgba8(buffer))
+}
+
 fn mean_center(tx: u32, ty: u32, dx: u32, dy: u32) -> f32 {
     let mut factor = 1f32;
 
@@ -115,6 +146,17 @@ impl fmt::Display for ArrayEmptyError {
 
 impl Error for ArrayEmptyError {}
 
+#[derive(Debug)]
+struct RasterizeError;
+
+impl fmt::Display for RasterizeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "SVG rasterization produced a zero-sized or mismatched target")
+    }
+}
+
+impl Error for RasterizeError {}
+
 pub fn addwatermark(
     logoimage: &Vec<Vec<Vec<f32>>>,
     basedimage: &mut Vec<Vec<Vec<f32>>>,
@@ -163,6 +205,20 @@ pub fn flip_vertical(tensor: &mut Vec<Vec<Vec<f32>>>) {
     }
 }
 
+#[allow(dead_code)]
+pub fn rotate90(tensor: &mut Vec<Vec<Vec<f32>>>) {
+    let (width, height) = (tensor[0].len(), tensor.len());
+    let mut rotated = vec![vec![vec![0f32; CHANNELS]; height]; width];
+
+    for y in 0..height {
+        for x in 0..width {
+            // clockwise: source (x, y) lands at (height - 1 - y, x)
+            rotated[x][height - y - 1] = tensor[y][x].clone();
+        }
+    }
+    *tensor = rotated;
+}
+
 #[allow(dead_code)]
 pub fn flip_horizontal(tensor: &mut Vec<Vec<Vec<f32>>>) {
     let (width, height) = (tensor[0].len(), tensor.len());
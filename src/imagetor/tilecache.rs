@@ -0,0 +1,172 @@
+//! A tiny lossy-but-cheap on-disk codec for watermarked RGBA tensors, so a
+//! second run can skip recomputing and re-encoding everything.
+//!
+//! The tensor is split into `TILE_SZ`×`TILE_SZ` tiles. Each tile's four
+//! channels are quantized from `f32` in `[0, 1]` to `u8` bucket indices with a
+//! configurable step (quant level 0 keeps all 256 buckets — lossless for `u8`
+//! source pixels — higher levels are coarser), then run-length encoded, since
+//! flat watermark and background regions produce long identical runs.
+
+use core::fmt;
+use std::error::Error;
+
+const MAGIC: &[u8; 4] = b"ITC1";
+const TILE_SZ: usize = 16;
+
+/// A cache byte stream that is truncated, mislabeled, or otherwise malformed.
+/// Callers treat this as a cache miss and recompute rather than aborting.
+#[derive(Debug)]
+pub struct CacheError;
+
+impl fmt::Display for CacheError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "malformed tile-cache stream")
+    }
+}
+
+impl Error for CacheError {}
+
+/// How aggressively tiles are quantized before RLE. Level 0 keeps the full 256
+/// buckets (lossless for 8-bit source pixels); each higher level doubles the
+/// bucket step and halves the effective resolution.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QualitySettings {
+    pub level: u8,
+}
+
+impl QualitySettings {
+    pub fn new(level: u8) -> Self {
+        Self { level }
+    }
+
+    /// The quantization step: a bucket index `i` dequantizes to `i * step`.
+    fn step(&self) -> f32 {
+        (1u32 << self.level) as f32 / 255.0
+    }
+}
+
+fn quantize(value: f32, step: f32) -> u8 {
+    (value.clamp(0.0, 1.0) / step).round() as u8
+}
+
+/// Encode `tensor` into the tile-cache byte stream. The layout is a fixed
+/// header (magic, width, height, channel count, tile size, quant level)
+/// followed by one RLE block per tile in row-major tile order; each block
+/// starts with the tile's real width and height (edge tiles are partial) and
+/// then lists `(value, count)` pairs per channel.
+pub fn encode_cache(tensor: &Vec<Vec<Vec<f32>>>, settings: QualitySettings) -> Vec<u8> {
+    let height = tensor.len();
+    let width = if height > 0 { tensor[0].len() } else { 0 };
+    let channels = if width > 0 { tensor[0][0].len() } else { 0 };
+    let step = settings.step();
+
+    let mut out = Vec::new();
+    out.extend_from_slice(MAGIC);
+    out.extend_from_slice(&(width as u32).to_le_bytes());
+    out.extend_from_slice(&(height as u32).to_le_bytes());
+    out.push(channels as u8);
+    out.push(TILE_SZ as u8);
+    out.push(settings.level);
+
+    let mut ty = 0;
+    while ty < height {
+        let th = TILE_SZ.min(height - ty);
+        let mut tx = 0;
+        while tx < width {
+            let tw = TILE_SZ.min(width - tx);
+            out.push(tw as u8);
+            out.push(th as u8);
+
+            for c in 0..channels {
+                // A u16 count lets a uniform tile (all-transparent or
+                // all-opaque) collapse to a single run.
+                let mut run_val: Option<u8> = None;
+                let mut run_len: u16 = 0;
+                for y in 0..th {
+                    for x in 0..tw {
+                        let q = quantize(tensor[ty + y][tx + x][c], step);
+                        match run_val {
+                            Some(v) if v == q && run_len < u16::MAX => run_len += 1,
+                            Some(v) => {
+                                out.push(v);
+                                out.extend_from_slice(&run_len.to_le_bytes());
+                                run_val = Some(q);
+                                run_len = 1;
+                            }
+                            None => {
+                                run_val = Some(q);
+                                run_len = 1;
+                            }
+                        }
+                    }
+                }
+                if let Some(v) = run_val {
+                    out.push(v);
+                    out.extend_from_slice(&run_len.to_le_bytes());
+                }
+            }
+            tx += TILE_SZ;
+        }
+        ty += TILE_SZ;
+    }
+    out
+}
+
+/// Reverse [`encode_cache`], rebuilding the RGBA tensor. Bucket indices are
+/// dequantized back to `f32` by multiplying by the header's quant step.
+pub fn decode_cache(data: &[u8]) -> Result<Vec<Vec<Vec<f32>>>, CacheError> {
+    if data.len() < 15 || &data[0..4] != MAGIC || data[13] as usize != TILE_SZ || data[14] >= 32 {
+        return Err(CacheError);
+    }
+
+    let width = u32::from_le_bytes([data[4], data[5], data[6], data[7]]) as usize;
+    let height = u32::from_le_bytes([data[8], data[9], data[10], data[11]]) as usize;
+    let channels = data[12] as usize;
+    let level = data[14];
+    let step = QualitySettings::new(level).step();
+
+    let mut tensor = vec![vec![vec![0f32; channels]; width]; height];
+    let mut pos = 15;
+
+    let mut ty = 0;
+    while ty < height {
+        let mut tx = 0;
+        while tx < width {
+            if pos + 2 > data.len() {
+                return Err(CacheError);
+            }
+            let tw = data[pos] as usize;
+            let th = data[pos + 1] as usize;
+            if tw != TILE_SZ.min(width - tx) || th != TILE_SZ.min(height - ty) {
+                return Err(CacheError);
+            }
+            pos += 2;
+
+            for c in 0..channels {
+                let total = tw * th;
+                let mut filled = 0;
+                while filled < total {
+                    if pos + 3 > data.len() {
+                        return Err(CacheError);
+                    }
+                    let val = data[pos];
+                    let count = u16::from_le_bytes([data[pos + 1], data[pos + 2]]) as usize;
+                    pos += 3;
+                    if count == 0 || filled + count > total {
+                        return Err(CacheError);
+                    }
+                    for i in 0..count {
+                        let idx = filled + i;
+                        let y = idx / tw;
+                        let x = idx % tw;
+                        tensor[ty + y][tx + x][c] = val as f32 * step;
+                    }
+                    filled += count;
+                }
+            }
+            tx += TILE_SZ;
+        }
+        ty += TILE_SZ;
+    }
+    Ok(tensor)
+}
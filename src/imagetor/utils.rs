@@ -1,9 +1,16 @@
 use core::fmt;
-use image::{codecs::jpeg::JpegEncoder, DynamicImage, GenericImageView};
-use printpdf::{ColorBits, Image, ImageTransform, ImageXObject, Mm, PdfDocument, Px};
+use image::{
+    codecs::{
+        bmp::BmpEncoder, gif::GifEncoder, jpeg::JpegEncoder, png::PngEncoder, webp::WebPEncoder,
+    },
+    ColorType, DynamicImage, Frame, GenericImageView, ImageBuffer, ImageEncoder, Rgba,
+};
+use printpdf::{
+    ColorBits, Image, ImageTransform, ImageXObject, Mm, PdfDocument, PdfLayerReference, Px,
+};
 use std::{
     fs::{self, File},
-    io::{self, BufWriter},
+    io::{self, BufWriter, Seek, Write},
     path::{Path, PathBuf},
 };
 
@@ -73,6 +80,7 @@ pub enum UtilsError {
     ImageError(image::ImageError),
     IOError(std::io::Error),
     PrintError(printpdf::errors::Error),
+    TiffError(tiff::TiffError),
 }
 
 impl fmt::Display for UtilsError {
@@ -81,6 +89,7 @@ impl fmt::Display for UtilsError {
             UtilsError::ImageError(ref e) => write!(f, "IOError: {}", e),
             UtilsError::IOError(ref e) => write!(f, "IOError: {}", e),
             UtilsError::PrintError(ref e) => write!(f, "PrintError: {}", e),
+            UtilsError::TiffError(ref e) => write!(f, "TiffError: {}", e),
         }
     }
 }
@@ -91,10 +100,17 @@ impl std::error::Error for UtilsError {
             UtilsError::ImageError(e) => Some(e),
             UtilsError::IOError(e) => Some(e),
             UtilsError::PrintError(e) => Some(e),
+            UtilsError::TiffError(e) => Some(e),
         }
     }
 }
 
+impl From<tiff::TiffError> for UtilsError {
+    fn from(e: tiff::TiffError) -> Self {
+        UtilsError::TiffError(e)
+    }
+}
+
 impl From<image::ImageError> for UtilsError {
     fn from(e: image::ImageError) -> Self {
         UtilsError::ImageError(e)
@@ -113,27 +129,304 @@ impl From<printpdf::errors::Error> for UtilsError {
     }
 }
 
+/// Read the EXIF Orientation tag (0x0112) from `path`, or `None` when the file
+/// carries no EXIF. Best-effort — any decode error reads as "no orientation".
+fn read_orientation(path: &Path) -> Option<u16> {
+    let file = File::open(path).ok()?;
+    let mut reader = io::BufReader::new(file);
+    let exif = exif::Reader::new().read_from_container(&mut reader).ok()?;
+    let field = exif.get_field(exif::Tag::Orientation, exif::In::PRIMARY)?;
+    field.value.get_uint(0).map(|v| v as u16)
+}
+
+/// Normalize `image` so its pixels display upright, following the EXIF
+/// orientation convention (1 = upright, 2/4 mirrors, 3 = 180°, 6/8 = 90° CW/CCW,
+/// 5/7 = the transposed diagonals).
+fn apply_orientation(image: DynamicImage, orientation: u16) -> DynamicImage {
+    use crate::imagetor::{flip_horizontal, flip_vertical, rotate90, to_image, to_tensor};
+
+    let mut tensor = to_tensor(image);
+    match orientation {
+        2 => flip_horizontal(&mut tensor),
+        3 => {
+            rotate90(&mut tensor);
+            rotate90(&mut tensor);
+        }
+        4 => flip_vertical(&mut tensor),
+        5 => {
+            rotate90(&mut tensor);
+            flip_horizontal(&mut tensor);
+        }
+        6 => rotate90(&mut tensor),
+        7 => {
+            rotate90(&mut tensor);
+            rotate90(&mut tensor);
+            rotate90(&mut tensor);
+            flip_horizontal(&mut tensor);
+        }
+        8 => {
+            rotate90(&mut tensor);
+            rotate90(&mut tensor);
+            rotate90(&mut tensor);
+        }
+        _ => {}
+    }
+    to_image(tensor)
+}
+
+/// Walk IFD0 of a raw EXIF (TIFF) block and force the Orientation value to 1.
+/// Silently leaves the block alone if it is truncated or has no such tag.
+fn reset_orientation(exif: &mut [u8]) {
+    if exif.len() < 8 {
+        return;
+    }
+    let little = match &exif[0..2] {
+        b"II" => true,
+        b"MM" => false,
+        _ => return,
+    };
+
+    let ifd = u32_at(exif, 4, little) as usize;
+    if ifd + 2 > exif.len() {
+        return;
+    }
+    let count = u16_at(exif, ifd, little) as usize;
+
+    for i in 0..count {
+        let entry = ifd + 2 + i * 12;
+        if entry + 12 > exif.len() {
+            return;
+        }
+        if u16_at(exif, entry, little) == 0x0112 {
+            let value = entry + 8;
+            let bytes = if little { 1u16.to_le_bytes() } else { 1u16.to_be_bytes() };
+            exif[value] = bytes[0];
+            exif[value + 1] = bytes[1];
+            return;
+        }
+    }
+}
+
+/// Encode the full RGBA buffer as a TIFF with the requested compression,
+/// skipping the lossy JPEG stage entirely.
+fn write_tiff<W: Write + Seek>(
+    writer: &mut W,
+    buffer: &ImageBuffer<Rgba<u8>, Vec<u8>>,
+    width: u32,
+    height: u32,
+    compression: TiffCompression,
+) -> Result<(), UtilsError> {
+    use tiff::encoder::{
+        colortype::RGBA8,
+        compression::{Deflate, Lzw, Packbits, Uncompressed},
+        TiffEncoder,
+    };
+
+    let mut encoder = TiffEncoder::new(writer)?;
+    let data = buffer.as_raw();
+    match compression {
+        TiffCompression::None => {
+            encoder.write_image_with_compression::<RGBA8, _>(width, height, Uncompressed, data)?
+        }
+        TiffCompression::PackBits => {
+            encoder.write_image_with_compression::<RGBA8, _>(width, height, Packbits, data)?
+        }
+        TiffCompression::Lzw => {
+            encoder.write_image_with_compression::<RGBA8, _>(width, height, Lzw, data)?
+        }
+        TiffCompression::Deflate => encoder.write_image_with_compression::<RGBA8, _>(
+            width,
+            height,
+            Deflate::default(),
+            data,
+        )?,
+    }
+    Ok(())
+}
+
+fn u16_at(buf: &[u8], offset: usize, little: bool) -> u16 {
+    let bytes = [buf[offset], buf[offset + 1]];
+    if little {
+        u16::from_le_bytes(bytes)
+    } else {
+        u16::from_be_bytes(bytes)
+    }
+}
+
+fn u32_at(buf: &[u8], offset: usize, little: bool) -> u32 {
+    let bytes = [
+        buf[offset],
+        buf[offset + 1],
+        buf[offset + 2],
+        buf[offset + 3],
+    ];
+    if little {
+        u32::from_le_bytes(bytes)
+    } else {
+        u32::from_be_bytes(bytes)
+    }
+}
+
+/// Output targets the save path knows how to write. Mirrors the subset of
+/// `image::ImageFormat` we actually encode, carrying the per-format knobs the
+/// encoder needs (JPEG quality, TIFF compression).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    Png,
+    Jpeg { quality: u8 },
+    WebP,
+    Tiff { compression: TiffCompression },
+    Bmp,
+    Gif,
+}
+
+/// Lossless TIFF compression schemes, mirroring the encoders the `tiff` crate
+/// provides. `None` is raw; `PackBits` is a cheap RLE fallback; `Lzw` and
+/// `Deflate` give the smallest archival files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TiffCompression {
+    None,
+    PackBits,
+    Lzw,
+    Deflate,
+}
+
+impl OutputFormat {
+    /// Map a bare file extension (no dot, any case) to the format that writes
+    /// it, or `None` when we have no encoder for it.
+    pub fn from_extension(ext: &str) -> Option<OutputFormat> {
+        match ext.to_ascii_lowercase().as_str() {
+            "png" => Some(OutputFormat::Png),
+            "jpg" | "jpeg" => Some(OutputFormat::Jpeg { quality: 100 }),
+            "webp" => Some(OutputFormat::WebP),
+            "tif" | "tiff" => Some(OutputFormat::Tiff {
+                compression: TiffCompression::Deflate,
+            }),
+            "bmp" => Some(OutputFormat::Bmp),
+            "gif" => Some(OutputFormat::Gif),
+            _ => None,
+        }
+    }
+}
+
+/// Discover every format `convert_image` can write, so a caller can present the
+/// choices without hard-coding the list.
+pub fn enumerate_supported_formats() -> Vec<OutputFormat> {
+    vec![
+        OutputFormat::Png,
+        OutputFormat::Jpeg { quality: 100 },
+        OutputFormat::WebP,
+        OutputFormat::Tiff {
+            compression: TiffCompression::Deflate,
+        },
+        OutputFormat::Bmp,
+        OutputFormat::Gif,
+    ]
+}
+
 impl Utils {
     pub fn open_image(self, path: &Path) -> Result<DynamicImage, image::ImageError> {
         let mut a = image::io::Reader::open(path)?;
         image::io::Reader::no_limits(&mut a);
-        a.decode()
+        let image = a.decode()?;
+
+        // Cameras store the sensor pixels as-shot and record how to display them
+        // in the EXIF Orientation tag. Bake that rotation into the pixels now so
+        // the watermark lands the right way up; a file without EXIF reads as 1.
+        match read_orientation(path) {
+            Some(orientation) if orientation != 1 => Ok(apply_orientation(image, orientation)),
+            _ => Ok(image),
+        }
     }
 
     pub fn save_image(self, tensor: Vec<Vec<Vec<f32>>>, filename: &str) -> Result<(), UtilsError> {
+        let format = Path::new(filename)
+            .extension()
+            .and_then(|e| e.to_str())
+            .and_then(OutputFormat::from_extension)
+            .unwrap_or(OutputFormat::Jpeg { quality: 100 });
+        self.convert_image(tensor, format, filename)
+    }
+
+    /// Encode `tensor` to `path` using `format`. Lossless formats keep the
+    /// alpha channel the tensor carries; JPEG flattens to RGB.
+    pub fn convert_image(
+        self,
+        tensor: Vec<Vec<Vec<f32>>>,
+        format: OutputFormat,
+        path: &str,
+    ) -> Result<(), UtilsError> {
         let image_buffer = to_image_buffer(tensor);
-        let file = File::create(filename).unwrap();
+        let (width, height) = (image_buffer.width(), image_buffer.height());
+        let file = File::create(path)?;
         let mut writer = BufWriter::new(file);
-        let mut encoder = JpegEncoder::new_with_quality(&mut writer, 100);
 
-        if let Err(e) = encoder.encode_image(&image_buffer) {
-            println!("Failed to save image: {}", e);
-            return Err(UtilsError::ImageError(e));
+        match format {
+            OutputFormat::Jpeg { quality } => {
+                let rgb = DynamicImage::ImageRgba8(image_buffer).to_rgb8();
+                JpegEncoder::new_with_quality(&mut writer, quality).encode_image(&rgb)?;
+            }
+            OutputFormat::Png => PngEncoder::new(&mut writer).write_image(
+                image_buffer.as_raw(),
+                width,
+                height,
+                ColorType::Rgba8,
+            )?,
+            OutputFormat::WebP => WebPEncoder::new_lossless(&mut writer).write_image(
+                image_buffer.as_raw(),
+                width,
+                height,
+                ColorType::Rgba8,
+            )?,
+            OutputFormat::Tiff { compression } => {
+                write_tiff(&mut writer, &image_buffer, width, height, compression)?
+            }
+            OutputFormat::Bmp => BmpEncoder::new(&mut writer).write_image(
+                image_buffer.as_raw(),
+                width,
+                height,
+                ColorType::Rgba8,
+            )?,
+            OutputFormat::Gif => {
+                GifEncoder::new(&mut writer).encode_frame(Frame::new(image_buffer))?
+            }
         }
+
         println!("Image saved successfully!");
         Ok(())
     }
 
+    /// Re-attach the EXIF block from `source` onto the JPEG just written at
+    /// `dest`, with Orientation reset to 1 so capture date, camera model and GPS
+    /// survive the watermarking round-trip without the viewer rotating twice.
+    /// Best-effort: a source with no EXIF, or a non-JPEG either end, is left
+    /// untouched.
+    pub fn copy_exif(&self, source: &Path, dest: &str) -> Result<(), UtilsError> {
+        use img_parts::jpeg::Jpeg;
+        use img_parts::{Bytes, ImageEXIF};
+
+        let src = match Jpeg::from_bytes(fs::read(source)?.into()) {
+            Ok(j) => j,
+            Err(_) => return Ok(()),
+        };
+        let mut exif = match src.exif() {
+            Some(e) => e.to_vec(),
+            None => return Ok(()),
+        };
+        reset_orientation(&mut exif);
+
+        let mut out = match Jpeg::from_bytes(fs::read(dest)?.into()) {
+            Ok(j) => j,
+            Err(_) => return Ok(()),
+        };
+        out.set_exif(Some(Bytes::from(exif)));
+
+        let file = File::create(dest)?;
+        let mut writer = BufWriter::new(file);
+        out.encoder().write_to(&mut writer)?;
+        Ok(())
+    }
+
     fn to_px(&self, value: f32) -> u32 {
         return (value / 25.4 * 300.0) as u32;
     }
@@ -153,6 +446,63 @@ impl Utils {
         let image_reader = image::io::Reader::new(reader).with_guessed_format();
         let reader = image_reader?;
         let img = reader.decode()?;
+
+        self.place_on_page(current_layer, &img, pdf_width_pixel, pdf_height_pixel);
+
+        let pdf_path = Path::new(filename).with_extension("pdf");
+        let pdffile = File::create(&pdf_path)?;
+        let mut writer = BufWriter::new(pdffile);
+
+        if let Err(e) = doc.save(&mut writer) {
+            return Err(UtilsError::PrintError(e));
+        }
+        Ok(())
+    }
+
+    /// Build one `PdfDocument` holding the whole batch, one watermarked image
+    /// centered per A4 page, and save it as `album.pdf`. The per-image
+    /// aspect-ratio/DPI placement is the same as [`generate_pdf`]; only the
+    /// document lifetime differs (shared instead of one-per-image).
+    pub fn generate_pdf_multipage(
+        &self,
+        images: &[(String, ImageBuffer<Rgba<u8>, Vec<u8>>)],
+    ) -> Result<(), UtilsError> {
+        let (pdf_width, pdf_height) = (Mm(210.0), Mm(297.0));
+        let (pdf_width_pixel, pdf_height_pixel) =
+            (self.to_px(pdf_width.0), self.to_px(pdf_height.0));
+
+        let (doc, first_page, first_layer) =
+            PdfDocument::new("album", pdf_width, pdf_height, "Vera Smith Design");
+
+        for (i, (_name, buffer)) in images.iter().enumerate() {
+            let (page, layer) = if i == 0 {
+                (first_page, first_layer)
+            } else {
+                doc.add_page(pdf_width, pdf_height, "Vera Smith Design")
+            };
+            let current_layer = doc.get_page(page).get_layer(layer);
+            let img = DynamicImage::ImageRgba8(buffer.clone());
+            self.place_on_page(current_layer, &img, pdf_width_pixel, pdf_height_pixel);
+        }
+
+        let pdffile = File::create("album.pdf")?;
+        let mut writer = BufWriter::new(pdffile);
+
+        if let Err(e) = doc.save(&mut writer) {
+            return Err(UtilsError::PrintError(e));
+        }
+        Ok(())
+    }
+
+    /// Place `img` centered on a single A4 page, scaling it down to fit while
+    /// keeping its aspect ratio at 300 DPI.
+    fn place_on_page(
+        &self,
+        layer: PdfLayerReference,
+        img: &DynamicImage,
+        pdf_width_pixel: u32,
+        pdf_height_pixel: u32,
+    ) {
         let (w, h) = img.dimensions();
 
         let img = img.to_rgb8();
@@ -186,7 +536,7 @@ impl Utils {
         let translate_y = translate_y as f32 / 300.0 * 25.4;
 
         Image::from(image).add_to_layer(
-            current_layer,
+            layer,
             ImageTransform {
                 translate_x: Some(Mm(translate_x)),
                 translate_y: Some(Mm(translate_y)),
@@ -196,13 +546,5 @@ impl Utils {
                 dpi: Some(300.0),
             },
         );
-
-        let pdffile = File::create(filename.replace(".jpg", ".pdf"))?;
-        let mut writer = BufWriter::new(pdffile);
-
-        if let Err(e) = doc.save(&mut writer) {
-            return Err(UtilsError::PrintError(e));
-        }
-        Ok(())
     }
 }
@@ -1,4 +1,4 @@
-use image::{self, GenericImageView};
+use image::{self, GenericImageView, ImageBuffer, Rgba};
 use std::{
     env::current_dir,
     io::{stdin, stdout, Read, Write},
@@ -7,7 +7,8 @@ use std::{
 };
 
 mod imagetor;
-use imagetor::{addwatermark, fit_center, to_tensor, utils::ImageFinder};
+use imagetor::tilecache::{self, QualitySettings};
+use imagetor::{addwatermark, fit_center, rasterize_svg_fit, to_tensor, utils::ImageFinder};
 use imagetor::{to_image_buffer, utils::Utils};
 
 fn main() {
@@ -22,12 +23,28 @@ fn main() {
 
     println!("Start to opening image ...");
 
-    // Opening logo
-    let logo_binding = current_path.join("logo.png");
-    let logo_path = Path::new(&logo_binding);
-    let logo = Utils.open_image(logo_path).unwrap();
-
-    println!("Logo Original size: {:?}", logo.dimensions());
+    // Combined mode (set `ALBUM_PDF`) collects every watermarked page and emits
+    // a single `album.pdf`; otherwise each image gets its own one-page PDF.
+    let combined_pdf = std::env::var("ALBUM_PDF").is_ok();
+    let mut album_pages: Vec<(String, ImageBuffer<Rgba<u8>, Vec<u8>>)> = Vec::new();
+
+    // Opening logo. A `logo.svg` is rasterized per-photo at the fit size so it
+    // stays crisp; otherwise fall back to the raster `logo.png`.
+    let svg_binding = current_path.join("logo.svg");
+    let svg_data = if svg_binding.exists() {
+        Some(std::fs::read(&svg_binding).unwrap())
+    } else {
+        None
+    };
+
+    let raster_logo = if svg_data.is_none() {
+        let logo_binding = current_path.join("logo.png");
+        let logo = Utils.open_image(Path::new(&logo_binding)).unwrap();
+        println!("Logo Original size: {:?}", logo.dimensions());
+        Some(logo)
+    } else {
+        None
+    };
 
     for (i, image) in images.iter().enumerate() {
         // Generating images path
@@ -39,29 +56,64 @@ fn main() {
         let image_binding = current_path.join(images_path.join(image));
         let image_path = Path::new(&image_binding);
 
-        // Opening image
-        let image = Utils.open_image(image_path).unwrap();
-
-        // Clone the logo to avoid modifying the original logo
-        let mut logo = logo.clone();
-        logo = fit_center(&logo, &image);
-
-        println!(
-            "Start to converting image to tensor ...resized: {:?}",
-            logo.dimensions()
-        );
-
-        // converting image and logo to 3D Tensor
-        let mut tensor1 = to_tensor(image);
-        let tensor2 = to_tensor(logo);
+        // Reuse a previously watermarked tensor when its cache is newer than
+        // the source; otherwise run the pipeline and write the cache.
+        let cache_dir = current_path.join("cache");
+        let _ = std::fs::create_dir_all(&cache_dir);
+        let cache_binding = cache_dir.join(format!("{}.itc", filename));
+        let cache_path = Path::new(&cache_binding);
+
+        // A truncated or corrupt cache file decodes to an error; treat that as
+        // a miss and recompute rather than crashing the whole run.
+        let cached = if cache_is_fresh(cache_path, image_path) {
+            std::fs::read(cache_path)
+                .ok()
+                .and_then(|bytes| tilecache::decode_cache(&bytes).ok())
+        } else {
+            None
+        };
 
-        println!("Start to adding watermark ...");
+        let tensor1 = if let Some(tensor1) = cached {
+            println!("Using cached tensor for {}", filename);
+            tensor1
+        } else {
+            // Opening image
+            let image = Utils.open_image(image_path).unwrap();
+
+            // Size the logo to this photo: rasterize the SVG at the fit size, or
+            // Lanczos-resize the raster logo.
+            let logo = match &svg_data {
+                Some(data) => match rasterize_svg_fit(data, &image) {
+                    Ok(logo) => logo,
+                    Err(e) => {
+                        println!("Failed to rasterize SVG logo for {}: {}", filename, e);
+                        continue;
+                    }
+                },
+                None => fit_center(raster_logo.as_ref().unwrap(), &image),
+            };
+
+            println!(
+                "Start to converting image to tensor ...resized: {:?}",
+                logo.dimensions()
+            );
+
+            // converting image and logo to 3D Tensor
+            let mut tensor1 = to_tensor(image);
+            let tensor2 = to_tensor(logo);
+
+            println!("Start to adding watermark ...");
+
+            if let Err(e) = addwatermark(&tensor2, &mut tensor1) {
+                println!("Failed to add watermark: {}", e);
+                return;
+            }
+            println!("watermark added successfully!");
 
-        if let Err(e) = addwatermark(&tensor2, &mut tensor1) {
-            println!("Failed to add watermark: {}", e);
-            return;
-        }
-        println!("watermark added successfully!");
+            let data = tilecache::encode_cache(&tensor1, QualitySettings::new(0));
+            let _ = std::fs::write(cache_path, data);
+            tensor1
+        };
 
         // use imagetor::{flip_horizontal, flip_vertical};
         // flip_vertical(&mut tensor1);
@@ -72,16 +124,34 @@ fn main() {
         let new_filename = &format!("output-{}", filename).replace(" ", "-");
         if let Ok(_ok) = Utils.save_image(tensor1.clone(), new_filename) {
             println!("{} saved successfully!", new_filename);
-            println!("Start to converting image to PDF ...");
-            let new_filename = new_filename.replace(".jpg", ".pdf");
-            if let Ok(()) = Utils.generate_pdf(&new_filename, to_image_buffer(tensor1)) {
-                println!("{} created successfully!", new_filename);
+
+            // Carry the camera's EXIF (date, model, GPS) across to the JPEG.
+            let lower = new_filename.to_ascii_lowercase();
+            if lower.ends_with(".jpg") || lower.ends_with(".jpeg") {
+                let _ = Utils.copy_exif(image_path, new_filename);
+            }
+            if combined_pdf {
+                // Defer to a single album.pdf built after the loop.
+                album_pages.push((new_filename.clone(), to_image_buffer(tensor1)));
+            } else {
+                println!("Start to converting image to PDF ...");
+                let pdf_path = Path::new(new_filename).with_extension("pdf");
+                if let Ok(()) = Utils.generate_pdf(new_filename) {
+                    println!("{} created successfully!", pdf_path.display());
+                }
             }
         } else {
             println!("Failed to save image: {}", new_filename);
         }
     }
 
+    if combined_pdf && !album_pages.is_empty() {
+        println!("Start to building combined album.pdf ...");
+        if let Ok(()) = Utils.generate_pdf_multipage(&album_pages) {
+            println!("album.pdf created successfully!");
+        }
+    }
+
     println!("All images processed successfully!");
 
     let elapsed: Duration = start_time.elapsed();
@@ -94,3 +164,17 @@ fn main() {
 
     let _ = stdin().read_exact(&mut [0]).unwrap();
 }
+
+/// True when `cache` exists and is at least as new as `source`, so the cached
+/// tensor still reflects the current source image.
+fn cache_is_fresh(cache: &Path, source: &Path) -> bool {
+    let cache_time = match std::fs::metadata(cache).and_then(|m| m.modified()) {
+        Ok(t) => t,
+        Err(_) => return false,
+    };
+    let source_time = match std::fs::metadata(source).and_then(|m| m.modified()) {
+        Ok(t) => t,
+        Err(_) => return false,
+    };
+    cache_time >= source_time
+}